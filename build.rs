@@ -1,12 +1,13 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet, VecDeque},
     env,
     error::Error,
     fs::{self, File},
     io::{self, BufWriter},
     path::{Path, PathBuf, MAIN_SEPARATOR_STR},
-    process::Command,
     result::Result as StdResult,
+    sync::{Mutex, OnceLock},
+    thread,
 };
 
 use std::io::Write;
@@ -17,8 +18,111 @@ use rquickjs::{
     module::ModuleData,
     CatchResultExt, CaughtError, Context, Ctx, Module, Runtime,
 };
+use serde::{Deserialize, Serialize};
 
 const BUNDLE_DIR: &str = "bundle";
+const CACHE_DIR: &str = "bundle_cache";
+const CACHE_MANIFEST_FILE: &str = "manifest.json";
+
+const CACHE_RELEVANT_FEATURES: &[&str] = &["lambda", "no-sdk", "uncompressed"];
+
+const EMBED_ASSET_EXTENSIONS: &[&str] = &["json", "wasm", "txt", "bin"];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EmbedKind {
+    Module,
+    Raw,
+}
+
+impl EmbedKind {
+    fn runtime_tag(self) -> u8 {
+        match self {
+            EmbedKind::Module => 0,
+            EmbedKind::Raw => 1,
+        }
+    }
+}
+
+const CHUNK_DIR: &str = "bundle_cache/chunks";
+
+// ~4 KiB average chunk size (FastCDC-style gear hash).
+const CDC_MIN_CHUNK_SIZE: usize = 1024;
+const CDC_MAX_CHUNK_SIZE: usize = 16 * 1024;
+const CDC_MASK_BITS: u32 = 12;
+
+fn gear_table() -> &'static [u64; 256] {
+    static TABLE: OnceLock<[u64; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        // Fixed seed so identical content always chunks identically.
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut table = [0u64; 256];
+        for slot in table.iter_mut() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            *slot = state;
+        }
+        table
+    })
+}
+
+fn cdc_boundaries(data: &[u8]) -> Vec<(usize, usize)> {
+    if data.is_empty() {
+        return vec![];
+    }
+
+    let table = gear_table();
+    let mask: u64 = (1u64 << CDC_MASK_BITS) - 1;
+
+    let mut boundaries = Vec::new();
+    let mut start = 0;
+    let mut hash: u64 = 0;
+
+    for (i, &byte) in data.iter().enumerate() {
+        hash = (hash << 1).wrapping_add(table[byte as usize]);
+        let size = i - start + 1;
+        if size >= CDC_MAX_CHUNK_SIZE || (size >= CDC_MIN_CHUNK_SIZE && hash & mask == 0) {
+            boundaries.push((start, i + 1));
+            start = i + 1;
+            hash = 0;
+        }
+    }
+
+    if start < data.len() {
+        boundaries.push((start, data.len()));
+    }
+
+    boundaries
+}
+
+#[derive(Default)]
+struct ChunkStore {
+    chunks: Vec<Vec<u8>>,
+    index_by_hash: HashMap<[u8; 16], u32>,
+}
+
+impl ChunkStore {
+    fn add(&mut self, data: &[u8]) -> Vec<u32> {
+        cdc_boundaries(data)
+            .into_iter()
+            .map(|(start, end)| self.intern(&data[start..end]))
+            .collect()
+    }
+
+    fn intern(&mut self, chunk: &[u8]) -> u32 {
+        let mut hash = [0u8; 16];
+        hash.copy_from_slice(&blake3::hash(chunk).as_bytes()[..16]);
+
+        if let Some(&index) = self.index_by_hash.get(&hash) {
+            return index;
+        }
+
+        let index = self.chunks.len() as u32;
+        self.chunks.push(chunk.to_vec());
+        self.index_by_hash.insert(hash, index);
+        index
+    }
+}
 
 include!("src/bytecode_meta.rs");
 
@@ -50,142 +154,465 @@ impl Resolver for DummyResolver {
     }
 }
 
-fn human_file_size(size: usize) -> String {
-    let fsize = size as f64;
-    let i = if size == 0 {
-        0
-    } else {
-        (fsize.log2() / 1024f64.log2()).floor() as i32
-    };
-    let size = fsize / 1024f64.powi(i);
-    let units = ["B", "kB", "MB", "GB", "TB", "PB"];
-    format!("{:.3} {}", size, units[i as usize])
+#[derive(Default, Serialize, Deserialize)]
+struct CacheManifest {
+    bytecode_version: String,
+    entries: HashMap<String, CacheEntry>,
 }
 
-#[tokio::main]
-async fn main() -> StdResult<(), Box<dyn Error>> {
-    rerun_if_changed!(BUNDLE_DIR);
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    source_hash: String,
+    bytecode_file: String,
+}
 
-    let resolver = (DummyResolver,);
-    let loader = (DummyLoader,);
+fn active_feature_set() -> String {
+    let mut enabled: Vec<&str> = CACHE_RELEVANT_FEATURES
+        .iter()
+        .copied()
+        .filter(|name| env::var(format!("CARGO_FEATURE_{}", name.to_uppercase().replace('-', "_"))).is_ok())
+        .collect();
+    enabled.sort_unstable();
+    enabled.join(",")
+}
 
-    let rt = Runtime::new()?;
-    rt.set_loader(resolver, loader);
-    let ctx = Context::full(&rt)?;
+fn module_source_hash(module_name: &str, source: &[u8]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(module_name.as_bytes());
+    hasher.update(source);
+    hasher.update(BYTECODE_VERSION.as_bytes());
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update(active_feature_set().as_bytes());
+    hasher.finalize().to_hex().to_string()
+}
 
-    let sdk_bytecode_path = Path::new("src").join("bytecode_cache.rs");
-    let mut sdk_bytecode_file = BufWriter::new(File::create(sdk_bytecode_path)?);
+fn load_cache_manifest(cache_dir: &Path) -> CacheManifest {
+    let manifest_path = cache_dir.join(CACHE_MANIFEST_FILE);
+
+    let manifest = fs::read_to_string(&manifest_path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<CacheManifest>(&contents).ok())
+        .unwrap_or_default();
+
+    if manifest.bytecode_version != BYTECODE_VERSION {
+        // Format changed since this cache was written; start fresh.
+        let _ = fs::remove_dir_all(cache_dir);
+        return CacheManifest {
+            bytecode_version: BYTECODE_VERSION.to_string(),
+            ..Default::default()
+        };
+    }
 
-    let mut ph_map = phf_codegen::Map::<String>::new();
-    let mut filenames = vec![];
-    let mut total_bytes: usize = 0;
+    manifest
+}
 
-    fs::write("VERSION", env!("CARGO_PKG_VERSION")).expect("Unable to write VERSION file");
+fn save_cache_manifest(cache_dir: &Path, manifest: &CacheManifest) -> io::Result<()> {
+    fs::create_dir_all(cache_dir)?;
+    let manifest_path = cache_dir.join(CACHE_MANIFEST_FILE);
+    let contents = serde_json::to_string_pretty(manifest)?;
+    fs::write(manifest_path, contents)
+}
+
+struct WalkEntry {
+    abs_path: PathBuf,
+    module_name: String,
+    embed_kind: EmbedKind,
+}
+
+struct CompiledEntry {
+    module_name: String,
+    embed_kind: EmbedKind,
+    bytes: Vec<u8>,
+    cache_entry: CacheEntry,
+}
 
-    ctx.with(|ctx| {
-        for dir_ent in WalkDir::new(BUNDLE_DIR).into_iter().flatten() {
-            let path = dir_ent.path();
+fn collect_bundle_entries() -> StdResult<Vec<WalkEntry>, Box<dyn Error>> {
+    let mut entries = vec![];
 
-            let path = path.strip_prefix(BUNDLE_DIR)?.to_owned();
-            let path_str = path.to_string_lossy().to_string();
+    for dir_ent in WalkDir::new(BUNDLE_DIR).into_iter().flatten() {
+        let abs_path = dir_ent.path();
 
-            if path_str.starts_with("__tests__") || path.extension().unwrap_or_default() != "js" {
+        let path = abs_path.strip_prefix(BUNDLE_DIR)?.to_owned();
+        let path_str = path.to_string_lossy().to_string();
+
+        let extension = path.extension().unwrap_or_default().to_string_lossy().to_string();
+
+        let embed_kind = match extension.as_str() {
+            "js" => EmbedKind::Module,
+            ext if EMBED_ASSET_EXTENSIONS.contains(&ext) => EmbedKind::Raw,
+            _ => continue,
+        };
+
+        if path_str.starts_with("__tests__") {
+            continue;
+        }
+
+        #[cfg(feature = "lambda")]
+        {
+            if path == PathBuf::new().join("@llrt").join("test.js") {
                 continue;
             }
+        }
 
-            #[cfg(feature = "lambda")]
+        #[cfg(feature = "no-sdk")]
+        {
+            if path_str.starts_with("@aws-sdk")
+                || path_str.starts_with("@smithy")
+                || path_str.starts_with("llrt-chunk-sdk")
             {
-                if path == PathBuf::new().join("@llrt").join("test.js") {
-                    continue;
-                }
+                continue;
             }
+        }
 
-            #[cfg(feature = "no-sdk")]
-            {
-                if path_str.starts_with("@aws-sdk")
-                    || path_str.starts_with("@smithy")
-                    || path_str.starts_with("llrt-chunk-sdk")
-                {
-                    continue;
-                }
+        let module_name = if !path_str.starts_with("llrt-chunk-") {
+            match embed_kind {
+                EmbedKind::Module => path.with_extension("").to_string_lossy().to_string(),
+                EmbedKind::Raw => path_str.clone(),
             }
+        } else {
+            path_str.clone()
+        };
+
+        entries.push(WalkEntry {
+            abs_path,
+            module_name,
+            embed_kind,
+        });
+    }
 
-            let source = fs::read_to_string(dir_ent.path())
-                .unwrap_or_else(|_| panic!("Unable to load: {}", dir_ent.path().to_string_lossy()));
-
-            let module_name = if !path_str.starts_with("llrt-chunk-") {
-                path.with_extension("").to_string_lossy().to_string()
-            } else {
-                path.to_string_lossy().to_string()
-            };
+    Ok(entries)
+}
 
-            info!("Compiling module: {}", module_name);
+fn embed_raw_asset(
+    entry: &WalkEntry,
+    cache_dir: &Path,
+) -> StdResult<CompiledEntry, Box<dyn Error>> {
+    info!("Embedding asset: {}", entry.module_name);
+
+    let bytes = fs::read(&entry.abs_path)
+        .unwrap_or_else(|_| panic!("Unable to load: {}", entry.abs_path.to_string_lossy()));
+
+    let source_hash = module_source_hash(&entry.module_name, &bytes);
+    let cached_blob_path = cache_dir.join(&source_hash);
+
+    fs::create_dir_all(cache_dir)?;
+    fs::write(&cached_blob_path, &bytes)?;
+
+    Ok(CompiledEntry {
+        module_name: entry.module_name.clone(),
+        embed_kind: EmbedKind::Raw,
+        bytes,
+        cache_entry: CacheEntry {
+            source_hash,
+            bytecode_file: cached_blob_path.to_string_lossy().to_string(),
+        },
+    })
+}
 
-            let filename = dir_ent
-                .path()
-                .with_extension(BYTECODE_EXT)
-                .to_string_lossy()
-                .to_string();
-            filenames.push(filename.clone());
+fn compile_module(
+    ctx: &Ctx<'_>,
+    entry: &WalkEntry,
+    cache_dir: &Path,
+    cache_manifest: &CacheManifest,
+) -> StdResult<CompiledEntry, Box<dyn Error>> {
+    info!("Compiling module: {}", entry.module_name);
+
+    let source = fs::read_to_string(&entry.abs_path)
+        .unwrap_or_else(|_| panic!("Unable to load: {}", entry.abs_path.to_string_lossy()));
+
+    let source_hash = module_source_hash(&entry.module_name, source.as_bytes());
+    let cached_blob_path = cache_dir.join(&source_hash);
+
+    let bytes = match cache_manifest.entries.get(&entry.module_name) {
+        Some(cached) if cached.source_hash == source_hash && cached_blob_path.is_file() => {
+            info!("Cache hit for module: {}", entry.module_name);
+            fs::read(&cached_blob_path).map_err(|err| err.to_string())?
+        },
+        _ => {
             let bytes = {
                 {
                     let module = unsafe {
-                        Module::unsafe_declare(ctx.clone(), module_name.clone(), source)
+                        Module::unsafe_declare(ctx.clone(), entry.module_name.clone(), source)
                     }?;
                     module.write_object(false)
                 }
             }
-            .catch(&ctx)
+            .catch(ctx)
             .map_err(|err| match err {
                 CaughtError::Error(error) => error.to_string(),
                 CaughtError::Exception(ex) => ex.to_string(),
                 CaughtError::Value(value) => format!("{:?}", value),
             })?;
 
-            total_bytes += bytes.len();
+            fs::create_dir_all(cache_dir)?;
+            fs::write(&cached_blob_path, &bytes)?;
+
+            bytes
+        },
+    };
+
+    info!("Done!");
+
+    Ok(CompiledEntry {
+        module_name: entry.module_name.clone(),
+        embed_kind: EmbedKind::Module,
+        bytes,
+        cache_entry: CacheEntry {
+            source_hash,
+            bytecode_file: cached_blob_path.to_string_lossy().to_string(),
+        },
+    })
+}
+
+// `Runtime`/`Context` aren't `Send`, so each worker thread owns its own.
+fn compile_modules_in_parallel(
+    queue: &Mutex<VecDeque<&WalkEntry>>,
+    cache_dir: &Path,
+    cache_manifest: &CacheManifest,
+) -> StdResult<Vec<CompiledEntry>, Box<dyn Error>> {
+    let worker_count = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..worker_count)
+            .map(|_| {
+                scope.spawn(|| -> StdResult<Vec<CompiledEntry>, String> {
+                    let resolver = (DummyResolver,);
+                    let loader = (DummyLoader,);
+
+                    let rt = Runtime::new().map_err(|err| err.to_string())?;
+                    rt.set_loader(resolver, loader);
+                    let ctx = Context::full(&rt).map_err(|err| err.to_string())?;
+
+                    let mut compiled = vec![];
+
+                    ctx.with(|ctx| -> StdResult<(), String> {
+                        loop {
+                            let entry = match queue.lock().unwrap().pop_front() {
+                                Some(entry) => entry,
+                                None => break,
+                            };
+
+                            compiled.push(
+                                compile_module(&ctx, entry, cache_dir, cache_manifest)
+                                    .map_err(|err| err.to_string())?,
+                            );
+                        }
+
+                        Ok(())
+                    })?;
+
+                    Ok(compiled)
+                })
+            })
+            .collect();
+
+        let mut compiled = vec![];
+        for handle in handles {
+            compiled.extend(handle.join().expect("worker thread panicked")?);
+        }
+
+        Ok(compiled)
+    })
+}
+
+fn human_file_size(size: usize) -> String {
+    let fsize = size as f64;
+    let i = if size == 0 {
+        0
+    } else {
+        (fsize.log2() / 1024f64.log2()).floor() as i32
+    };
+    let size = fsize / 1024f64.powi(i);
+    let units = ["B", "kB", "MB", "GB", "TB", "PB"];
+    format!("{:.3} {}", size, units[i as usize])
+}
+
+#[tokio::main]
+async fn main() -> StdResult<(), Box<dyn Error>> {
+    rerun_if_changed!(BUNDLE_DIR);
+
+    let sdk_bytecode_path = Path::new("src").join("bytecode_cache.rs");
+    let mut sdk_bytecode_file = BufWriter::new(File::create(sdk_bytecode_path)?);
+
+    let mut ph_map = phf_codegen::Map::<String>::new();
+    let mut module_blobs: Vec<(String, EmbedKind, Vec<u8>)> = vec![];
+    let mut total_bytes: usize = 0;
+
+    fs::write("VERSION", env!("CARGO_PKG_VERSION")).expect("Unable to write VERSION file");
+
+    let cache_dir = PathBuf::from(CACHE_DIR);
+    let mut cache_manifest = load_cache_manifest(&cache_dir);
+    let mut fresh_cache_manifest = CacheManifest {
+        bytecode_version: BYTECODE_VERSION.to_string(),
+        ..Default::default()
+    };
+
+    let bundle_entries = collect_bundle_entries()?;
+
+    for entry in bundle_entries.iter().filter(|e| e.embed_kind == EmbedKind::Raw) {
+        let compiled = embed_raw_asset(entry, &cache_dir)?;
+        fresh_cache_manifest
+            .entries
+            .insert(compiled.module_name.clone(), compiled.cache_entry);
+        total_bytes += compiled.bytes.len();
+        module_blobs.push((compiled.module_name, compiled.embed_kind, compiled.bytes));
+    }
+
+    let module_entries: VecDeque<&WalkEntry> = bundle_entries
+        .iter()
+        .filter(|e| e.embed_kind == EmbedKind::Module)
+        .collect();
+    let work_queue = Mutex::new(module_entries);
+
+    for compiled in compile_modules_in_parallel(&work_queue, &cache_dir, &cache_manifest)? {
+        fresh_cache_manifest
+            .entries
+            .insert(compiled.module_name.clone(), compiled.cache_entry);
+        total_bytes += compiled.bytes.len();
+        module_blobs.push((compiled.module_name, compiled.embed_kind, compiled.bytes));
+    }
+
+    save_cache_manifest(&cache_dir, &fresh_cache_manifest)?;
+    let live_blobs: HashSet<String> = fresh_cache_manifest
+        .entries
+        .values()
+        .map(|entry| entry.bytecode_file.clone())
+        .collect();
+    for entry in cache_manifest.entries.drain() {
+        if !live_blobs.contains(&entry.1.bytecode_file) {
+            let _ = fs::remove_file(&entry.1.bytecode_file);
+        }
+    }
+
+    // Sort so ChunkStore::add's first-seen-wins indices don't depend on
+    // worker thread scheduling order.
+    module_blobs.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut chunk_store = ChunkStore::default();
+    for (module_name, embed_kind, bytes) in &module_blobs {
+        let indices = chunk_store.add(bytes);
+        ph_map.entry(
+            module_name.clone(),
+            &format!(
+                "BytecodeEntry {{ kind: {}, chunk_indices: &[{}] }}",
+                embed_kind.runtime_tag(),
+                indices
+                    .iter()
+                    .map(|index| format!("{}u32", index))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        );
+    }
+
+    // Clear stale chunk files a previous, larger build may have left behind.
+    let _ = fs::remove_dir_all(CHUNK_DIR);
+    fs::create_dir_all(CHUNK_DIR)?;
+    let chunk_filenames: Vec<String> = chunk_store
+        .chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let filename = Path::new(CHUNK_DIR)
+                .join(format!("{index}.{BYTECODE_EXT}"))
+                .to_string_lossy()
+                .to_string();
 
             if cfg!(feature = "uncompressed") {
-                let mut uncompressed = Vec::with_capacity(4 + 6 + bytes.len());
+                let mut uncompressed = Vec::with_capacity(4 + 6 + chunk.len());
                 uncompressed.extend_from_slice(BYTECODE_VERSION.as_bytes());
-                uncompressed.extend_from_slice(&[BYTECODE_UNCOMPRESSED]); //uncompressed
-                uncompressed.extend_from_slice(&bytes);
+                uncompressed.extend_from_slice(&[BYTECODE_UNCOMPRESSED]);
+                uncompressed.extend_from_slice(chunk);
                 fs::write(&filename, uncompressed).unwrap();
             } else {
-                fs::write(&filename, bytes).unwrap();
+                fs::write(&filename, chunk).unwrap();
             }
 
-            info!("Done!");
+            filename
+        })
+        .collect();
 
-            ph_map.entry(
-                module_name,
-                &format!("include_bytes!(\"..{}{}\")", MAIN_SEPARATOR_STR, &filename),
-            );
-        }
+    let chunk_table_entries = chunk_filenames
+        .iter()
+        .map(|filename| format!("include_bytes!(\"..{}{}\")", MAIN_SEPARATOR_STR, filename))
+        .collect::<Vec<_>>()
+        .join(",\n    ");
 
-        StdResult::<_, Box<dyn Error>>::Ok(())
-    })?;
+    let compression_dictionary_path = Path::new(BUNDLE_DIR)
+        .join("compression.dict")
+        .to_string_lossy()
+        .to_string();
+    let dictionary_include = format!("include_bytes!(\"..{}{}\")", MAIN_SEPARATOR_STR, compression_dictionary_path);
 
     write!(
         &mut sdk_bytecode_file,
-        "// @generated by build.rs\n\npub static BYTECODE_CACHE: phf::Map<&'static str, &[u8]> = {}",
-        ph_map.build()
+        "// @generated by build.rs\n\n\
+         /// A `BUNDLE_DIR` entry compiled to QuickJS bytecode; load with `write_object`.\n\
+         pub const EMBED_KIND_MODULE: u8 = {module_tag};\n\
+         /// A `BUNDLE_DIR` entry embedded verbatim; read the reconstructed bytes as-is.\n\
+         pub const EMBED_KIND_RAW: u8 = {raw_tag};\n\n\
+         /// A `BYTECODE_CACHE` entry: which chunks to reassemble, and whether the\n\
+         /// result is QuickJS bytecode (`EMBED_KIND_MODULE`) or a raw asset\n\
+         /// (`EMBED_KIND_RAW`).\n\
+         pub struct BytecodeEntry {{\n    \
+             pub kind: u8,\n    \
+             pub chunk_indices: &'static [u32],\n\
+         }}\n\n\
+         pub static CHUNK_TABLE: &[&[u8]] = &[\n    {chunks}\n];\n\n\
+         pub static BYTECODE_CACHE: phf::Map<&'static str, BytecodeEntry> = {map}",
+        module_tag = EmbedKind::Module.runtime_tag(),
+        raw_tag = EmbedKind::Raw.runtime_tag(),
+        chunks = chunk_table_entries,
+        map = ph_map.build()
     )?;
     writeln!(&mut sdk_bytecode_file, ";")?;
 
+    // Each `CHUNK_TABLE` entry is its own self-contained
+    // `BYTECODE_VERSION + flag(+size) + payload` blob (see `compress_bytecode`),
+    // so reassembling a module means decoding every one of its chunks
+    // individually before concatenating the *decoded* bytes; a raw
+    // concatenation of the still-encoded chunks would splice zstd frames and
+    // headers into the middle of what's supposed to be one bytecode stream.
+    write!(
+        &mut sdk_bytecode_file,
+        "\nstatic COMPRESSION_DICTIONARY: &[u8] = {dictionary_include};\n\n\
+         fn decode_chunk(raw: &[u8]) -> Vec<u8> {{\n    \
+             let flag = raw[{version_len}];\n    \
+             let payload = &raw[{version_len} + 1..];\n    \
+             if flag == {compressed_tag} {{\n        \
+                 let uncompressed_len = u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]) as usize;\n        \
+                 zstd::bulk::Decompressor::with_dictionary(COMPRESSION_DICTIONARY)\n            \
+                     .expect(\"invalid compression dictionary\")\n            \
+                     .decompress(&payload[4..], uncompressed_len)\n            \
+                     .expect(\"corrupt compressed chunk\")\n    \
+             }} else {{\n        \
+                 payload.to_vec()\n    \
+             }}\n}}\n\n\
+             pub fn reconstruct(entry: &BytecodeEntry) -> Vec<u8> {{\n    \
+                 entry\n        \
+                     .chunk_indices\n        \
+                     .iter()\n        \
+                     .flat_map(|i| decode_chunk(CHUNK_TABLE[*i as usize]))\n        \
+                     .collect()\n}}\n",
+        dictionary_include = dictionary_include,
+        version_len = BYTECODE_VERSION.len(),
+        compressed_tag = BYTECODE_COMPRESSED,
+    )?;
+
     info!(
         "\n===============================\nUncompressed bytecode size: {}\n===============================",
         human_file_size(total_bytes)
     );
-
-    let compression_dictionary_path = Path::new(BUNDLE_DIR)
-        .join("compression.dict")
-        .to_string_lossy()
-        .to_string();
+    info!(
+        "Deduplicated into {} unique chunks (from {} modules/assets)",
+        chunk_store.chunks.len(),
+        module_blobs.len()
+    );
 
     if cfg!(feature = "uncompressed") {
-        generate_compression_dictionary(&compression_dictionary_path, &filenames)?;
+        generate_compression_dictionary(&compression_dictionary_path, &chunk_filenames)?;
     } else {
-        total_bytes = compress_bytecode(compression_dictionary_path, filenames)?;
+        total_bytes = compress_bytecode(compression_dictionary_path, chunk_filenames)?;
 
         info!(
             "\n===============================\nCompressed bytecode size: {}\n===============================",
@@ -196,55 +623,36 @@ async fn main() -> StdResult<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Matches the previous `zstd --maxdict=20K` CLI invocation.
+const COMPRESSION_DICTIONARY_MAX_SIZE: usize = 20 * 1024;
+/// `--ultra -22`: the highest zstd compression level.
+const COMPRESSION_LEVEL: i32 = 22;
+
 fn compress_bytecode(dictionary_path: String, source_files: Vec<String>) -> io::Result<usize> {
-    generate_compression_dictionary(&dictionary_path, &source_files)?;
+    let dictionary = generate_compression_dictionary(&dictionary_path, &source_files)?;
 
     let mut total_size = 0;
-    let tmp_dir = env::temp_dir();
+
+    // Reused across chunks: constructing a `Compressor` re-digests the
+    // dictionary, which isn't cheap to pay per chunk.
+    let mut compressor = zstd::bulk::Compressor::with_dictionary(COMPRESSION_LEVEL, &dictionary)?;
 
     for filename in source_files {
         info!("Compressing {}...", filename);
 
-        let tmp_filename = tmp_dir
-            .join(nanoid::nanoid!())
-            .to_string_lossy()
-            .to_string();
-
-        fs::copy(&filename, &tmp_filename)?;
-
-        let uncompressed_file_size = PathBuf::from(&filename).metadata().unwrap().len() as u32;
-
-        let output = Command::new("zstd")
-            .args([
-                "--ultra",
-                "-22",
-                "-f",
-                "-D",
-                &dictionary_path,
-                &tmp_filename,
-                "-o",
-                &filename,
-            ])
-            .output()?;
-
-        if !output.status.success() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Failed to compress file",
-            ));
-        }
+        let source = fs::read(&filename)?;
+        let uncompressed_file_size = source.len() as u32;
+
+        let bytes = compressor.compress(&source)?;
 
-        let bytes = fs::read(&filename)?;
         let mut compressed = Vec::with_capacity(4 + 6 + bytes.len());
         compressed.extend_from_slice(BYTECODE_VERSION.as_bytes());
         compressed.extend_from_slice(&[BYTECODE_COMPRESSED]); //compressed
         compressed.extend_from_slice(&uncompressed_file_size.to_le_bytes());
         compressed.extend_from_slice(&bytes);
-        fs::write(&filename, compressed)?;
-
-        let compressed_file_size = PathBuf::from(&filename).metadata().unwrap().len() as usize;
+        fs::write(&filename, &compressed)?;
 
-        total_size += compressed_file_size;
+        total_size += compressed.len();
     }
 
     Ok(total_size)
@@ -252,36 +660,24 @@ fn compress_bytecode(dictionary_path: String, source_files: Vec<String>) -> io::
 
 fn generate_compression_dictionary(
     dictionary_path: &str,
-    source_files: &Vec<String>,
-) -> Result<(), io::Error> {
+    source_files: &[String],
+) -> io::Result<Vec<u8>> {
     info!("Generating compression dictionary...");
-    let file_count = source_files.len();
-    let mut dictionary_filenames = source_files.clone();
-    let mut dictionary_file_set: HashSet<String> = HashSet::from_iter(dictionary_filenames.clone());
-    let mut cmd = Command::new("zstd");
-    cmd.args([
-        "--train",
-        "--train-fastcover=steps=40",
-        "--maxdict=20K",
-        "-o",
-        dictionary_path,
-    ]);
-    if file_count < 5 {
-        dictionary_file_set.retain(|file_path| {
-            let metadata = fs::metadata(file_path).unwrap();
-            let file_size = metadata.len();
-            file_size >= 1024 // 1 kilobyte = 1024 bytes
-        });
-        cmd.arg("-B1K");
-        dictionary_filenames = dictionary_file_set.into_iter().collect();
+
+    // Too few samples (e.g. `--no-sdk` builds) makes tiny ones dilute training.
+    let mut samples: Vec<Vec<u8>> = source_files
+        .iter()
+        .map(fs::read)
+        .collect::<io::Result<_>>()?;
+
+    if samples.len() < 5 {
+        samples.retain(|sample| sample.len() >= 1024);
     }
-    cmd.args(&dictionary_filenames);
-    let mut cmd = cmd.args(source_files).spawn()?;
-    let exit_status = cmd.wait()?;
-    Ok(if !exit_status.success() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            "Failed to generate compression dictionary",
-        ));
-    })
+
+    let dictionary = zstd::dict::from_samples(&samples, COMPRESSION_DICTIONARY_MAX_SIZE)
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+    fs::write(dictionary_path, &dictionary)?;
+
+    Ok(dictionary)
 }
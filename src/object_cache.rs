@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     mem,
     ptr::NonNull,
     sync::{RwLock, RwLockReadGuard},
@@ -9,7 +10,7 @@ use rquickjs::{
     atom::PredefinedAtom,
     function::Constructor,
     qjs::{self, JSValue, JSValueUnion, JS_DupContext, JS_DupValue},
-    Ctx, Exception, Function, Object, Result, Value,
+    Ctx, Function, Object, Result, Value,
 };
 
 pub struct ObjectCache {
@@ -17,7 +18,16 @@ pub struct ObjectCache {
     cache: [qjs::JSValue; 256],
 }
 
-static OBJECT_CACHE: OnceCell<RwLock<Option<ObjectCache>>> = OnceCell::new();
+/// One `ObjectCache` per `JSContext`, keyed by the context's raw pointer, so
+/// concurrently running `Runtime`/`Context` pairs each get their own
+/// `Map`/`Set`/`Date`/`Buffer` handles instead of racing over a single global
+/// slot. `get_value` pays an extra `HashMap` lookup under this lock per call
+/// versus the old single-slot design's direct field access.
+static OBJECT_CACHES: OnceCell<RwLock<HashMap<usize, ObjectCache>>> = OnceCell::new();
+
+fn ctx_key(ctx: &Ctx<'_>) -> usize {
+    ctx.as_raw().as_ptr() as usize
+}
 
 trait EnumIndex {
     fn index(self) -> usize;
@@ -65,11 +75,15 @@ impl EnumIndex for FunctionCacheKey {
     }
 }
 
-impl ObjectCache {
+pub struct ObjectCacheGuard<'a> {
+    caches: RwLockReadGuard<'a, HashMap<usize, ObjectCache>>,
+    key: usize,
+}
+
+impl ObjectCacheGuard<'_> {
     #[inline(always)]
-    pub fn get<'a>() -> RwLockReadGuard<'a, Option<ObjectCache>> {
-        let cache = OBJECT_CACHE.get().unwrap();
-        cache.read().unwrap()
+    fn cache(&self) -> Option<&ObjectCache> {
+        self.caches.get(&self.key)
     }
 
     pub fn get_function<'js>(&self, key: FunctionCacheKey) -> Result<Function<'js>> {
@@ -87,13 +101,27 @@ impl ObjectCache {
 
     #[inline(always)]
     fn get_value<'js>(&self, key: impl EnumIndex) -> Value<'js> {
-        let ctx = unsafe { Ctx::from_raw(self.ctx) };
-        let cached_value = self.cache[key.index()];
+        let cache = self
+            .cache()
+            .expect("ObjectCache not initialized for this context");
+        let ctx = unsafe { Ctx::from_raw(cache.ctx) };
+        let cached_value = cache.cache[key.index()];
         let js_value = unsafe { JS_DupValue(cached_value) };
         unsafe { Value::from_raw(ctx, js_value) }
     }
 }
 
+impl ObjectCache {
+    #[inline(always)]
+    pub fn get<'a>(ctx: &Ctx<'_>) -> ObjectCacheGuard<'a> {
+        let caches = OBJECT_CACHES.get().unwrap();
+        ObjectCacheGuard {
+            caches: caches.read().unwrap(),
+            key: ctx_key(ctx),
+        }
+    }
+}
+
 fn append_cache(cache: &mut [JSValue; 256], map: impl EnumIndex, object: Object<'_>) {
     let ctx = object.ctx();
     let value = object.as_raw();
@@ -103,21 +131,25 @@ fn append_cache(cache: &mut [JSValue; 256], map: impl EnumIndex, object: Object<
     cache[map.index()] = value
 }
 
-pub fn clear() {
-    let cache = OBJECT_CACHE.get().unwrap();
-
-    if let Some(cache) = cache.write().unwrap().take() {
-        for value in cache.cache {
-            unsafe {
-                if !value.u.ptr.is_null() {
-                    qjs::JS_FreeValue(cache.ctx.as_ptr(), value);
-                }
-            }
-        }
+fn free_cache(cache: ObjectCache) {
+    for value in cache.cache {
         unsafe {
-            qjs::JS_FreeContext(cache.ctx.as_ptr());
+            if !value.u.ptr.is_null() {
+                qjs::JS_FreeValue(cache.ctx.as_ptr(), value);
+            }
         }
     }
+    unsafe {
+        qjs::JS_FreeContext(cache.ctx.as_ptr());
+    }
+}
+
+pub fn clear(ctx: &Ctx<'_>) {
+    let caches = OBJECT_CACHES.get().unwrap();
+
+    if let Some(cache) = caches.write().unwrap().remove(&ctx_key(ctx)) {
+        free_cache(cache);
+    }
 }
 
 pub fn init(ctx: &Ctx) -> Result<()> {
@@ -194,9 +226,13 @@ pub fn init(ctx: &Ctx) -> Result<()> {
         cache: values,
     };
 
-    OBJECT_CACHE
-        .set(RwLock::new(Some(cache)))
-        .map_err(|_| Exception::throw_message(ctx, "ObjectCache already inited!"))?;
+    let caches = OBJECT_CACHES.get_or_init(|| RwLock::new(HashMap::new()));
+    if let Some(previous) = caches.write().unwrap().insert(ctx_key(ctx), cache) {
+        // Re-initializing the same context (e.g. after a reload): release
+        // the stale entry instead of leaking it.
+        free_cache(previous);
+    }
+
     Ok(())
 }
 